@@ -1,27 +1,121 @@
 use crate::{AccessToken, App, Error, Installation};
+use async_trait::async_trait;
 use chrono::{Duration, Utc};
 use reqwest::Client;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Where [`Cache`] persists installation access tokens between requests (and, if the
+/// implementation is backed by durable storage, between process restarts).
+///
+/// To share tokens across a horizontally-scaled deployment, implement this trait over a service
+/// such as Redis: `get` as a `GET`/`HGET`, `put` as a `SET`/`HSET` with the token's TTL derived
+/// from `access_token.expires_at`.
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn get(&self, installation_id: u64) -> Result<Option<AccessToken>, Error>;
+    async fn put(&self, installation_id: u64, access_token: AccessToken) -> Result<(), Error>;
+}
+
+/// The default [`TokenStore`]: an in-process map that is lost on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    tokens: Mutex<HashMap<u64, AccessToken>>,
+}
+
+#[async_trait]
+impl TokenStore for MemoryStore {
+    async fn get(&self, installation_id: u64) -> Result<Option<AccessToken>, Error> {
+        Ok(self.tokens.lock().await.get(&installation_id).cloned())
+    }
+
+    async fn put(&self, installation_id: u64, access_token: AccessToken) -> Result<(), Error> {
+        self.tokens.lock().await.insert(installation_id, access_token);
+        Ok(())
+    }
+}
+
+/// A [`TokenStore`] that serializes tokens to a JSON file, so they survive process restarts.
+/// Reads and writes are serialized through an in-process lock; this does not coordinate across
+/// multiple processes sharing the same file.
+pub struct FileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn read_all(&self) -> Result<HashMap<u64, AccessToken>, Error> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Writes to a temp file in the same directory and renames it into place, so a crash or power
+    // loss mid-write can never leave `self.path` holding a truncated/partial file that permanently
+    // breaks every subsequent `read_all`.
+    async fn write_all(&self, tokens: &HashMap<u64, AccessToken>) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_vec(tokens)?).await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TokenStore for FileStore {
+    async fn get(&self, installation_id: u64) -> Result<Option<AccessToken>, Error> {
+        let _guard = self.lock.lock().await;
+        Ok(self.read_all().await?.remove(&installation_id))
+    }
+
+    async fn put(&self, installation_id: u64, access_token: AccessToken) -> Result<(), Error> {
+        let _guard = self.lock.lock().await;
+        let mut tokens = self.read_all().await?;
+        tokens.insert(installation_id, access_token);
+        self.write_all(&tokens).await
+    }
+}
+
 #[derive(Clone)]
-pub struct Cache {
-    inner: Arc<Inner>,
+pub struct Cache<S = MemoryStore> {
+    inner: Arc<Inner<S>>,
 }
 
-struct Inner {
+struct Inner<S> {
     app: App,
-    cache: Mutex<HashMap<u64, AccessToken>>,
+    store: S,
+    // One lock per installation, created on first use, so concurrent callers racing near expiry
+    // for the *same* installation serialize on the refresh instead of each independently minting
+    // (and clobbering each other's `put` of) a fresh token; different installations don't block
+    // each other.
+    refreshes: Mutex<HashMap<u64, Arc<Mutex<()>>>>,
 }
 
-impl Cache {
+impl Cache<MemoryStore> {
     pub fn new(app: App) -> Self {
+        Self::with_store(app, MemoryStore::default())
+    }
+}
+
+impl<S: TokenStore> Cache<S> {
+    pub fn with_store(app: App, store: S) -> Self {
         Self {
             inner: Arc::new(Inner {
                 app,
-                cache: Mutex::new(HashMap::new()),
+                store,
+                refreshes: Mutex::new(HashMap::new()),
             }),
         }
     }
@@ -35,19 +129,39 @@ impl Cache {
         client: &Client,
         installation: &Installation,
     ) -> Result<AccessToken, Error> {
-        let mut cache = self.inner.cache.lock().await;
-        let access_token = match cache.entry(installation.id) {
-            Entry::Occupied(entry) => {
-                let access_token = entry.into_mut();
-                if access_token.expires_at < Utc::now() + Duration::seconds(60) {
-                    *access_token = self.inner.app.access_token(client, installation).await?;
-                }
-                access_token
-            }
-            Entry::Vacant(entry) => {
-                entry.insert(self.inner.app.access_token(client, installation).await?)
-            }
-        };
-        Ok(access_token.clone())
+        if let Some(access_token) = self.fresh_token(installation.id).await? {
+            return Ok(access_token);
+        }
+
+        let refresh_lock = self
+            .inner
+            .refreshes
+            .lock()
+            .await
+            .entry(installation.id)
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone();
+        let _guard = refresh_lock.lock().await;
+
+        // Another caller may have refreshed while we were waiting for the lock above.
+        if let Some(access_token) = self.fresh_token(installation.id).await? {
+            return Ok(access_token);
+        }
+
+        let access_token = self.inner.app.access_token(client, installation).await?;
+        self.inner
+            .store
+            .put(installation.id, access_token.clone())
+            .await?;
+        Ok(access_token)
+    }
+
+    async fn fresh_token(&self, installation_id: u64) -> Result<Option<AccessToken>, Error> {
+        Ok(self
+            .inner
+            .store
+            .get(installation_id)
+            .await?
+            .filter(|access_token| access_token.expires_at >= Utc::now() + Duration::seconds(60)))
     }
 }
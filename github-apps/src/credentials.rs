@@ -0,0 +1,82 @@
+use crate::{etag, paginate_stream, AccessToken, App, Error, EtagCache, RetryConfig};
+use futures::stream::Stream;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+/// How a request authenticates itself to the GitHub API.
+pub enum Credentials {
+    /// A personal-access-token or OAuth user-to-server token, sent as `Authorization: token <..>`.
+    Token(String),
+    /// A GitHub App, authenticating as itself via a freshly minted JWT.
+    App(App),
+    /// A GitHub App installation access token, sent as `Authorization: bearer <..>`.
+    InstallationToken(AccessToken),
+}
+
+impl Credentials {
+    async fn authenticate(&self, builder: RequestBuilder) -> Result<RequestBuilder, Error> {
+        let builder = builder.header(ACCEPT, "application/vnd.github.v3+json");
+        Ok(match self {
+            Self::Token(token) => builder.header(AUTHORIZATION, format!("token {}", token)),
+            Self::App(app) => builder.bearer_auth(app.jwt().await?),
+            Self::InstallationToken(access_token) => builder.bearer_auth(&access_token.token),
+        })
+    }
+}
+
+/// A [`reqwest::Client`] paired with [`Credentials`], so callers who only have a personal-access-
+/// token (or an OAuth user-to-server token) don't need to reimplement request building just to
+/// make authenticated reads.
+pub struct AuthenticatedClient {
+    client: Client,
+    credentials: Credentials,
+    retry: RetryConfig,
+    etag_cache: Option<EtagCache>,
+}
+
+impl AuthenticatedClient {
+    pub fn new(client: Client, credentials: Credentials) -> Self {
+        Self {
+            client,
+            credentials,
+            retry: RetryConfig::default(),
+            etag_cache: None,
+        }
+    }
+
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// See [`crate::App::with_etag_cache`].
+    pub fn with_etag_cache(mut self, etag_cache: EtagCache) -> Self {
+        self.etag_cache = Some(etag_cache);
+        self
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, url: Url) -> Result<T, Error> {
+        let builder = self
+            .credentials
+            .authenticate(self.client.get(url.clone()))
+            .await?;
+        Ok(etag::get(builder, url, &self.retry, self.etag_cache.as_ref())
+            .await?
+            .body)
+    }
+
+    pub fn get_stream<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        url: Url,
+    ) -> impl Stream<Item = Result<T, Error>> + 'a {
+        paginate_stream(
+            &self.client,
+            url,
+            &self.retry,
+            self.etag_cache.as_ref(),
+            move |builder| self.credentials.authenticate(builder),
+        )
+    }
+}
@@ -1,4 +1,5 @@
-use reqwest::Response;
+use chrono::{DateTime, Utc};
+use reqwest::{Response, StatusCode};
 use serde::Deserialize;
 use url::Url;
 
@@ -9,17 +10,39 @@ pub enum Error {
     #[error(transparent)]
     Hyper(#[from] hyper::Error),
     #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
     Jsonwebtoken(#[from] jsonwebtoken::errors::Error),
+    #[error("rate limited, resets at {reset}")]
+    RateLimited { reset: DateTime<Utc> },
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
     #[error(transparent)]
     ReqwestHeaderToStr(#[from] reqwest::header::ToStrError),
     #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+    #[error(transparent)]
     UrlParse(#[from] url::ParseError),
+    #[error("unknown webhook event {0:?}")]
+    UnknownWebhookEvent(String),
+    #[error("webhook signature does not match")]
+    WebhookSignatureMismatch,
 }
 
 impl Error {
     pub(crate) async fn check_rest_api_response(response: Response) -> Result<Response, Self> {
+        let status = response.status();
+        if status.is_success() {
+            Ok(response)
+        } else {
+            Err(Self::from_rest_api_error_body(status, response.text().await?))
+        }
+    }
+
+    // Builds a `RestApi` error from an already-read error body, for callers (like the retry
+    // layer) that need to inspect the body before deciding whether `response` even *is* the final
+    // error, and so can't hand the `Response` to `check_rest_api_response` unconsumed.
+    pub(crate) fn from_rest_api_error_body(status: StatusCode, text: String) -> Self {
         #[derive(Deserialize)]
         struct Payload {
             #[serde(default)]
@@ -30,26 +53,20 @@ impl Error {
             documentation_url: Option<Url>,
         }
 
-        let status = response.status();
-        if status.is_success() {
-            Ok(response)
+        if let Ok(payload) = serde_json::from_str::<Payload>(&text) {
+            Self::RestApi(rest_api::Error {
+                status,
+                message: payload.message,
+                errors: payload.errors,
+                documentation_url: payload.documentation_url,
+            })
         } else {
-            let text = response.text().await?;
-            if let Ok(payload) = serde_json::from_str::<Payload>(&text) {
-                Err(Self::RestApi(rest_api::Error {
-                    status,
-                    message: payload.message,
-                    errors: payload.errors,
-                    documentation_url: payload.documentation_url,
-                }))
-            } else {
-                Err(Self::RestApi(rest_api::Error {
-                    status,
-                    message: Some(text),
-                    errors: None,
-                    documentation_url: None,
-                }))
-            }
+            Self::RestApi(rest_api::Error {
+                status,
+                message: Some(text),
+                errors: None,
+                documentation_url: None,
+            })
         }
     }
 }
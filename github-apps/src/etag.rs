@@ -0,0 +1,119 @@
+use crate::{retry, Error, RetryConfig};
+use reqwest::header::{AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use url::Url;
+
+#[derive(Clone)]
+struct Entry {
+    etag: String,
+    body: Vec<u8>,
+}
+
+// Entries are keyed by the `Authorization` header value (if any) alongside the URL, not the URL
+// alone: the same literal URL (e.g. `<endpoint>/app/installations`) means something different per
+// caller identity, and GitHub's validators are not guaranteed to vary per-identity on their own.
+// Without this, a conditional request built from one identity's cached ETag but sent with another
+// identity's credentials could short-circuit to a 304 and serve the wrong caller's cached body.
+type CacheKey = (Option<String>, Url);
+
+/// An optional per-`(identity, URL)` cache of the last `ETag` and response body seen for a GET
+/// request.
+///
+/// A conditional request (`If-None-Match`) that comes back `304 Not Modified` does not count
+/// against GitHub's primary rate limit, so a polling consumer that repeatedly lists installations
+/// or repositories can walk an unchanged page chain for free. Share one `EtagCache` (it's cheaply
+/// `Clone`) across calls to benefit; leave it unconfigured to opt out of the memory cost. Requests
+/// authenticated as an `App` carry a JWT minted per request, so their entries are only matched by
+/// a request sent within the same second (see [`crate::App::with_etag_cache`]). Entries are never evicted, so a long-running
+/// process that hits many distinct (identity, URL) pairs will grow this cache without bound; keep
+/// one per bounded set of callers/URLs (e.g. per installation) if that matters.
+#[derive(Clone, Default)]
+pub struct EtagCache {
+    entries: Arc<Mutex<HashMap<CacheKey, Entry>>>,
+}
+
+impl EtagCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+pub(crate) struct Fetched<T> {
+    pub body: T,
+    pub link: Option<String>,
+}
+
+// Sends `builder`, attaching `If-None-Match` when `cache` already holds an `ETag` for this
+// identity and `url`. On `304 Not Modified`, deserializes the cached bytes instead of
+// re-downloading the body; on a fresh body, (re-)populates the cache for next time. The `Link` header is threaded through
+// unparsed, since the response carries it regardless of whether the body came from cache.
+pub(crate) async fn get<T: DeserializeOwned>(
+    builder: RequestBuilder,
+    url: Url,
+    retry: &RetryConfig,
+    cache: Option<&EtagCache>,
+) -> Result<Fetched<T>, Error> {
+    let identity = builder
+        .try_clone()
+        .and_then(|builder| builder.build().ok())
+        .and_then(|request| {
+            request
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        });
+    let key = (identity, url);
+
+    let cached = match cache {
+        Some(cache) => cache.entries.lock().await.get(&key).cloned(),
+        None => None,
+    };
+    let builder = match &cached {
+        Some(entry) => builder.header(IF_NONE_MATCH, entry.etag.clone()),
+        None => builder,
+    };
+    let (identity, url) = key;
+
+    let response = retry::send(builder, retry).await?;
+    let link = response
+        .headers()
+        .get(reqwest::header::LINK)
+        .map(|value| value.to_str())
+        .transpose()?
+        .map(str::to_owned);
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(entry) = cached {
+            return Ok(Fetched {
+                body: serde_json::from_slice(&entry.body)?,
+                link,
+            });
+        }
+    }
+
+    let response = Error::check_rest_api_response(response).await?;
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let bytes = response.bytes().await?;
+    if let (Some(cache), Some(etag)) = (cache, etag) {
+        cache.entries.lock().await.insert(
+            (identity, url),
+            Entry {
+                etag,
+                body: bytes.to_vec(),
+            },
+        );
+    }
+    Ok(Fetched {
+        body: serde_json::from_slice(&bytes)?,
+        link,
+    })
+}
@@ -0,0 +1,49 @@
+use crate::Error;
+use async_trait::async_trait;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::Serialize;
+
+// https://docs.github.com/en/developers/apps/authenticating-with-github-apps#authenticating-as-a-github-app
+#[derive(Serialize)]
+pub struct Claims {
+    pub iat: i64,
+    pub exp: i64,
+    pub iss: u64,
+}
+
+/// Mints the JWT an [`crate::App`] uses to authenticate as itself. The default `LocalRsaSigner`
+/// signs in-process with an `EncodingKey`; implement this trait to delegate signing to a remote
+/// key custodian (AWS KMS, Google Cloud HSM, a Cloudflare Worker binding) that never exposes the
+/// raw private key to the process, or to run on targets (e.g. `wasm32`) where local RSA signing
+/// doesn't build.
+///
+/// `?Send`: a `wasm32-unknown-unknown` signer backed by `wasm_bindgen_futures::JsFuture` (e.g. a
+/// Cloudflare Worker KMS binding) returns a future that is not `Send`, so `sign` must not require
+/// one. `Sync` is kept so `Box<dyn JwtSigner>` (and therefore [`crate::App`]) stays shareable
+/// across threads.
+#[async_trait(?Send)]
+pub trait JwtSigner: Sync {
+    async fn sign(&self, claims: Claims) -> Result<String, Error>;
+}
+
+/// Signs locally with an in-memory RSA private key, via `jsonwebtoken::encode`.
+pub struct LocalRsaSigner {
+    key: EncodingKey,
+}
+
+impl LocalRsaSigner {
+    pub fn new(key: EncodingKey) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait(?Send)]
+impl JwtSigner for LocalRsaSigner {
+    async fn sign(&self, claims: Claims) -> Result<String, Error> {
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &self.key,
+        )?)
+    }
+}
@@ -1,23 +1,37 @@
 mod cache;
+mod credentials;
 mod error;
+mod etag;
+mod jwt;
+mod retry;
+pub mod webhook;
 
-pub use cache::Cache;
+pub use cache::{Cache, FileStore, MemoryStore, TokenStore};
 use chrono::{DateTime, Duration, Utc};
+pub use credentials::{AuthenticatedClient, Credentials};
 pub use error::Error;
+pub use etag::EtagCache;
+use futures::stream::{self, Stream, TryStreamExt};
 use hyper::header::{Link, RelationType};
 pub use jsonwebtoken::EncodingKey;
-use jsonwebtoken::{Algorithm, Header};
-use reqwest::header::{ACCEPT, LINK};
+use jwt::Claims;
+pub use jwt::{JwtSigner, LocalRsaSigner};
+use reqwest::header::ACCEPT;
 pub use reqwest::Client;
 use reqwest::RequestBuilder;
+pub use retry::RetryConfig;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
 pub use url::Url;
 
 pub struct App {
     endpoint: Url,
     id: u64,
-    private_key: EncodingKey,
+    signer: Box<dyn JwtSigner>,
+    retry: RetryConfig,
+    etag_cache: Option<EtagCache>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -27,7 +41,7 @@ pub struct Installation {
     pub repositories_url: Url,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AccessToken {
     pub token: String,
     pub expires_at: DateTime<Utc>,
@@ -35,47 +49,81 @@ pub struct AccessToken {
 
 impl App {
     pub fn new(endpoint: Url, id: u64, private_key: EncodingKey) -> Self {
+        Self::with_signer(endpoint, id, Box::new(LocalRsaSigner::new(private_key)))
+    }
+
+    /// Like [`App::new`], but delegates JWT signing to a custom [`JwtSigner`] instead of holding
+    /// the raw private key in process (e.g. a KMS/HSM-backed signer, or one usable on `wasm32`).
+    pub fn with_signer(endpoint: Url, id: u64, signer: Box<dyn JwtSigner>) -> Self {
         Self {
             endpoint,
             id,
-            private_key,
+            signer,
+            retry: RetryConfig::default(),
+            etag_cache: None,
         }
     }
 
-    // https://docs.github.com/en/developers/apps/authenticating-with-github-apps#authenticating-as-a-github-app
-    fn jwt(&self) -> Result<String, Error> {
-        #[derive(Serialize)]
-        struct Payload {
-            iat: i64,
-            exp: i64,
-            iss: u64,
-        }
+    /// Overrides the default rate-limit retry behaviour (up to 5 retries after the first attempt,
+    /// capped at 5 minutes of sleep per retry).
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Caches `ETag`s (and the raw bodies they were issued for) per `(identity, URL)`, so a
+    /// subsequent unchanged GET with the same identity costs a `304` instead of a full download,
+    /// and doesn't count against the primary rate limit; the cached body is still deserialized
+    /// again. Off by default. Entries are keyed by the `Authorization` header, and an `App` signs
+    /// a fresh JWT (with second-granularity `iat`/`exp`) for every request, so an entry is only
+    /// matched by a request sent within the same second as the one that stored it.
+    pub fn with_etag_cache(mut self, etag_cache: EtagCache) -> Self {
+        self.etag_cache = Some(etag_cache);
+        self
+    }
 
+    // https://docs.github.com/en/developers/apps/authenticating-with-github-apps#authenticating-as-a-github-app
+    async fn jwt(&self) -> Result<String, Error> {
         let iat = Utc::now();
-        Ok(jsonwebtoken::encode(
-            &Header::new(Algorithm::RS256),
-            &Payload {
+        self.signer
+            .sign(Claims {
                 iat: (iat - Duration::seconds(60)).timestamp(),
                 exp: (iat + Duration::seconds(10 * 60)).timestamp(),
                 iss: self.id,
-            },
-            &self.private_key,
-        )?)
+            })
+            .await
     }
 
     // https://docs.github.com/en/rest/reference/apps#list-installations-for-the-authenticated-app
     pub async fn installations(&self, client: &Client) -> Result<Vec<Installation>, Error> {
+        self.installations_stream(client).try_collect().await
+    }
+
+    // https://docs.github.com/en/rest/reference/apps#list-installations-for-the-authenticated-app
+    //
+    // Unlike `installations`, this does not wait for the whole `Link: rel="next"` chain to be
+    // walked before yielding anything: pages are fetched lazily as the stream is polled past the
+    // items already buffered from the current page.
+    pub fn installations_stream<'a>(
+        &'a self,
+        client: &'a Client,
+    ) -> impl Stream<Item = Result<Installation, Error>> + 'a {
         let mut url = self.endpoint.clone();
         url.path_segments_mut()
             .unwrap()
             .push("app")
             .push("installations");
-        pagination(client, url, |builder| {
-            Ok(builder
-                .header(ACCEPT, "application/vnd.github.v3+json")
-                .bearer_auth(self.jwt()?))
-        })
-        .await
+        paginate_stream(
+            client,
+            url,
+            &self.retry,
+            self.etag_cache.as_ref(),
+            move |builder| async move {
+                Ok(builder
+                    .header(ACCEPT, "application/vnd.github.v3+json")
+                    .bearer_auth(self.jwt().await?))
+            },
+        )
     }
 
     // https://docs.github.com/en/rest/reference/apps#create-an-installation-access-token-for-an-app
@@ -84,17 +132,16 @@ impl App {
         client: &Client,
         installation: &Installation,
     ) -> Result<AccessToken, Error> {
-        Ok(Error::check_status(
-            client
-                .post(installation.access_tokens_url.clone())
-                .header(ACCEPT, "application/vnd.github.v3+json")
-                .bearer_auth(self.jwt()?)
-                .send()
+        let builder = client
+            .post(installation.access_tokens_url.clone())
+            .header(ACCEPT, "application/vnd.github.v3+json")
+            .bearer_auth(self.jwt().await?);
+        Ok(
+            Error::check_rest_api_response(retry::send(builder, &self.retry).await?)
+                .await?
+                .json()
                 .await?,
         )
-        .await?
-        .json()
-        .await?)
     }
 
     // https://docs.github.com/en/rest/reference/apps#get-a-repository-installation-for-the-authenticated-app
@@ -111,44 +158,59 @@ impl App {
             .push(owner)
             .push(repo)
             .push("installation");
-        Ok(Error::check_status(
-            client
-                .get(url)
-                .header(ACCEPT, "application/vnd.github.v3+json")
-                .bearer_auth(self.jwt()?)
-                .send()
-                .await?,
-        )
-        .await?
-        .json()
-        .await?)
+        let builder = client
+            .get(url.clone())
+            .header(ACCEPT, "application/vnd.github.v3+json")
+            .bearer_auth(self.jwt().await?);
+        Ok(etag::get(builder, url, &self.retry, self.etag_cache.as_ref())
+            .await?
+            .body)
     }
 }
 
-async fn pagination<F, T>(client: &Client, url: Url, mut f: F) -> Result<Vec<T>, Error>
+// Walks the `Link: rel="next"` chain, yielding each item as soon as it is decoded instead of
+// buffering every page into a `Vec` first. The next page is only fetched once the consumer has
+// pulled past the items already buffered from the current one.
+fn paginate_stream<'a, F, Fut, T>(
+    client: &'a Client,
+    url: Url,
+    retry: &'a RetryConfig,
+    etag_cache: Option<&'a EtagCache>,
+    f: F,
+) -> impl Stream<Item = Result<T, Error>> + 'a
 where
-    F: FnMut(RequestBuilder) -> Result<RequestBuilder, Error>,
-    T: DeserializeOwned,
+    F: Fn(RequestBuilder) -> Fut + 'a,
+    Fut: Future<Output = Result<RequestBuilder, Error>> + 'a,
+    T: DeserializeOwned + 'a,
 {
-    let mut items = Vec::new();
-    let mut url = Some(url);
-    while let Some(u) = url.take() {
-        let response = Error::check_status(f(client.get(u))?.send().await?).await?;
-        if let Some(link) = response.headers().get(LINK) {
-            url = link
-                .to_str()?
-                .parse::<Link>()?
-                .values()
-                .iter()
-                .find_map(|link_value| {
-                    link_value.rel().and_then(|rel| {
-                        rel.contains(&RelationType::Next)
-                            .then(|| Url::parse(link_value.link()))
-                    })
-                })
-                .transpose()?;
-        }
-        items.append(&mut response.json().await?);
-    }
-    Ok(items)
+    stream::try_unfold(
+        (Some(url), VecDeque::new()),
+        move |(mut url, mut buffer)| async move {
+            loop {
+                if let Some(item) = buffer.pop_front() {
+                    return Ok(Some((item, (url, buffer))));
+                }
+                let u = match url.take() {
+                    Some(u) => u,
+                    None => return Ok(None),
+                };
+                let builder = f(client.get(u.clone())).await?;
+                let fetched = etag::get::<Vec<T>>(builder, u, retry, etag_cache).await?;
+                if let Some(link) = fetched.link {
+                    url = link
+                        .parse::<Link>()?
+                        .values()
+                        .iter()
+                        .find_map(|link_value| {
+                            link_value.rel().and_then(|rel| {
+                                rel.contains(&RelationType::Next)
+                                    .then(|| Url::parse(link_value.link()))
+                            })
+                        })
+                        .transpose()?;
+                }
+                buffer = fetched.body.into();
+            }
+        },
+    )
 }
@@ -0,0 +1,190 @@
+use crate::Error;
+use chrono::{DateTime, TimeZone, Utc};
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+/// Tuning knobs for the rate-limit backoff that [`crate::App`] applies to every outbound request.
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub max_sleep: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            max_sleep: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+// Sends `builder`, transparently retrying on GitHub's primary (`x-ratelimit-remaining: 0`) and
+// secondary (documented `Retry-After`-less 403/429 with a matching error message) rate limiting,
+// per https://docs.github.com/en/rest/overview/resources-in-the-rest-api#rate-limiting
+//
+// A bare 403/429 with neither rate-limit headers nor that message (e.g. "Bad credentials",
+// "Resource not accessible by integration") is a real client error, not rate limiting: its body
+// is turned into the `RestApi` error right away instead of being retried.
+pub(crate) async fn send(builder: RequestBuilder, config: &RetryConfig) -> Result<Response, Error> {
+    for attempt in 0.. {
+        let request = builder
+            .try_clone()
+            .expect("request body must support cloning for retries");
+        let response = request.send().await?;
+        match handle_response(response, attempt, config).await? {
+            Outcome::Done(response) => return Ok(response),
+            Outcome::Retry(sleep) => tokio::time::sleep(sleep).await,
+        }
+    }
+    unreachable!()
+}
+
+enum Outcome {
+    Done(Response),
+    Retry(Duration),
+}
+
+// Decides what `send` should do with one response: pass it through as-is, or (if it's rate
+// limiting with genuine evidence and retries remain) sleep and retry. Split out from `send` so
+// the branching can be exercised directly in tests against a canned `Response`, without an actual
+// HTTP round trip.
+async fn handle_response(
+    response: Response,
+    attempt: u32,
+    config: &RetryConfig,
+) -> Result<Outcome, Error> {
+    let status = response.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return Ok(Outcome::Done(response));
+    }
+
+    let retry_after = retry_after(response.headers());
+    let reset = rate_limit_reset(response.headers());
+    if retry_after.is_none() && reset.is_none() {
+        let text = response.text().await?;
+        if !is_secondary_rate_limit_message(&text) {
+            return Err(Error::from_rest_api_error_body(status, text));
+        }
+    }
+
+    if attempt >= config.max_retries {
+        return Err(Error::RateLimited {
+            reset: reset
+                .or_else(|| {
+                    retry_after
+                        .and_then(|delay| chrono::Duration::from_std(delay).ok())
+                        .map(|delay| Utc::now() + delay)
+                })
+                .unwrap_or_else(Utc::now),
+        });
+    }
+
+    let sleep = retry_after
+        .or_else(|| reset.map(|reset| (reset - Utc::now()).to_std().unwrap_or_default()))
+        .unwrap_or_else(|| secondary_rate_limit_backoff(attempt))
+        .min(config.max_sleep);
+    Ok(Outcome::Retry(sleep))
+}
+
+// https://docs.github.com/en/rest/using-the-rest-api/best-practices-for-using-the-rest-api#dealing-with-secondary-rate-limits
+fn is_secondary_rate_limit_message(text: &str) -> bool {
+    text.to_ascii_lowercase().contains("secondary rate limit")
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_secs)
+}
+
+fn rate_limit_reset(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let remaining: u64 = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())?;
+    if remaining != 0 {
+        return None;
+    }
+    let reset: i64 = headers
+        .get("x-ratelimit-reset")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())?;
+    Utc.timestamp_opt(reset, 0).single()
+}
+
+// Exponential backoff with jitter for secondary rate limits, which GitHub signals with a bare
+// 403/429 and no `Retry-After` or `x-ratelimit-*` headers.
+fn secondary_rate_limit_backoff(attempt: u32) -> Duration {
+    let base = 2u64.saturating_pow(attempt);
+    let jitter = rand::thread_rng().gen_range(0..1000);
+    Duration::from_secs(base) + Duration::from_millis(jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(body.to_owned()).unwrap().into()
+    }
+
+    fn config(max_retries: u32) -> RetryConfig {
+        RetryConfig {
+            max_retries,
+            max_sleep: Duration::from_secs(5 * 60),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_genuine_403_passes_through_as_the_real_error() {
+        let response = response(403, &[], "Bad credentials");
+        let err = handle_response(response, 0, &config(5)).await.unwrap_err();
+        assert!(matches!(err, Error::RestApi(rest_api) if rest_api.message.as_deref() == Some("Bad credentials")));
+    }
+
+    #[tokio::test]
+    async fn a_secondary_rate_limit_message_is_retried() {
+        let response = response(
+            403,
+            &[],
+            r#"{"message": "You have exceeded a secondary rate limit"}"#,
+        );
+        match handle_response(response, 0, &config(5)).await.unwrap() {
+            Outcome::Retry(_) => {}
+            Outcome::Done(_) => panic!("expected a retry"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_primary_rate_limit_is_retried_via_headers() {
+        let reset = (Utc::now() + chrono::Duration::seconds(30)).timestamp();
+        let response = response(
+            403,
+            &[
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", &reset.to_string()),
+            ],
+            "",
+        );
+        match handle_response(response, 0, &config(5)).await.unwrap() {
+            Outcome::Retry(sleep) => assert!(sleep <= Duration::from_secs(30)),
+            Outcome::Done(_) => panic!("expected a retry"),
+        }
+    }
+
+    #[tokio::test]
+    async fn gives_up_with_rate_limited_once_retries_are_exhausted() {
+        let response = response(429, &[("Retry-After", "1")], "");
+        let err = handle_response(response, 5, &config(5)).await.unwrap_err();
+        assert!(matches!(err, Error::RateLimited { .. }));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{Error, Installation};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+//
+// Verifies that `body` (the raw, undecoded request body) was signed by GitHub with `secret`, by
+// recomputing the HMAC-SHA256 digest and comparing it against the delivery's
+// `X-Hub-Signature-256` header in constant time.
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> Result<(), Error> {
+    let expected = signature
+        .strip_prefix("sha256=")
+        .ok_or(Error::WebhookSignatureMismatch)?;
+    let expected = hex::decode(expected).map_err(|_| Error::WebhookSignatureMismatch)?;
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret).expect("HMAC accepts a key of any length");
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| Error::WebhookSignatureMismatch)
+}
+
+/// Common webhook delivery payloads, as distinguished by the `X-GitHub-Event` header. Parse one
+/// with [`parse_event`] once [`verify_signature`] has confirmed the delivery is genuine.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event {
+    Installation(InstallationEvent),
+    Ping(PingEvent),
+}
+
+// https://docs.github.com/en/webhooks/webhook-events-and-payloads#installation
+#[derive(Clone, Debug, Deserialize)]
+pub struct InstallationEvent {
+    pub action: String,
+    pub installation: Installation,
+}
+
+// https://docs.github.com/en/webhooks/webhook-events-and-payloads#ping
+#[derive(Clone, Debug, Deserialize)]
+pub struct PingEvent {
+    pub zen: String,
+    pub hook_id: u64,
+}
+
+pub fn parse_event(event_name: &str, body: &[u8]) -> Result<Event, Error> {
+    match event_name {
+        "installation" => Ok(Event::Installation(serde_json::from_slice(body)?)),
+        "ping" => Ok(Event::Ping(serde_json::from_slice(body)?)),
+        _ => Err(Error::UnknownWebhookEvent(event_name.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"it's a secret to everybody";
+    const BODY: &[u8] = br#"{"zen":"Responsive is better than fast."}"#;
+
+    fn sign(secret: &[u8], body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn verify_signature_accepts_a_matching_signature() {
+        let signature = sign(SECRET, BODY);
+        verify_signature(SECRET, BODY, &signature).unwrap();
+    }
+
+    #[test]
+    fn verify_signature_rejects_a_tampered_body() {
+        let signature = sign(SECRET, BODY);
+        let tampered = br#"{"zen":"Evil is better than fast."}"#;
+        assert!(matches!(
+            verify_signature(SECRET, tampered, &signature),
+            Err(Error::WebhookSignatureMismatch)
+        ));
+    }
+
+    #[test]
+    fn verify_signature_rejects_a_mismatched_secret() {
+        let signature = sign(b"wrong secret", BODY);
+        assert!(matches!(
+            verify_signature(SECRET, BODY, &signature),
+            Err(Error::WebhookSignatureMismatch)
+        ));
+    }
+
+    #[test]
+    fn verify_signature_rejects_a_missing_sha256_prefix() {
+        let signature = sign(SECRET, BODY);
+        let without_prefix = signature.strip_prefix("sha256=").unwrap();
+        assert!(matches!(
+            verify_signature(SECRET, BODY, without_prefix),
+            Err(Error::WebhookSignatureMismatch)
+        ));
+    }
+
+    #[test]
+    fn verify_signature_rejects_invalid_hex() {
+        assert!(matches!(
+            verify_signature(SECRET, BODY, "sha256=not-hex"),
+            Err(Error::WebhookSignatureMismatch)
+        ));
+    }
+}